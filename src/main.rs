@@ -1,9 +1,9 @@
-use std::env;
 use std::path::PathBuf;
 
 use clap::{Arg, ArgAction, command, value_parser};
 
 use audio_recorder::recorder;
+use audio_recorder::recorder::{Encoding, SampleFormat};
 
 static USER_ERROR: i32 = 2;
 
@@ -14,14 +14,47 @@ fn main() {
         .arg(Arg::new("output").short('o').long("output").required(false).value_parser(value_parser!(PathBuf)))
         .arg(Arg::new("stream").long("stream").required(false).action(ArgAction::SetTrue).help("Stream audio to stdout instead of writing to a file"))
         .arg(Arg::new("lib").long("lib").required(false).value_parser(value_parser!(PathBuf)))
-        .arg(Arg::new("play").short('p').long("play").required(false).value_parser(value_parser!(PathBuf)).help("Play a file instead of recording"))
+        .arg(Arg::new("play").short('p').long("play").required(false).num_args(1..).value_parser(value_parser!(PathBuf)).help("Play one or more files or directories instead of recording"))
+        .arg(Arg::new("shuffle").long("shuffle").required(false).action(ArgAction::SetTrue).requires("play").help("Shuffle the playback queue"))
+        .arg(Arg::new("repeat").long("repeat").required(false).action(ArgAction::SetTrue).requires("play").help("Loop the playback queue"))
+        .arg(Arg::new("volume").long("volume").required(false).requires("play").value_parser(value_parser!(f32)).help("Playback volume, 0.0 to 2.0 (1.0 is the source volume)"))
+        .arg(Arg::new("speed").long("speed").required(false).requires("play").value_parser(value_parser!(f32)).help("Playback speed multiplier"))
+        .arg(Arg::new("interactive").short('i').long("interactive").required(false).action(ArgAction::SetTrue).requires("play").help("Control playback from stdin: space pauses/resumes, +/- nudge the volume, q stops"))
         .arg(Arg::new("list").short('l').long("list").required(false).action(ArgAction::SetTrue))
-        .arg(Arg::new("stop-silence").short('s').long("stop-silence").required(false).value_parser(value_parser!(u64)).help("Stop recording after this many milliseconds of silence"))
+        .arg(Arg::new("stop-silence").short('s').long("stop-silence").required(false).value_parser(value_parser!(u64)).help("Stop recording (or split, with --split-on-silence) after this many milliseconds below --silence-threshold"))
+        .arg(Arg::new("silence-threshold").long("silence-threshold").required(false).default_value("-50.0").value_parser(value_parser!(f32)).help("Level below which audio is considered silent, in dBFS"))
+        .arg(Arg::new("split-on-silence").long("split-on-silence").required(false).action(ArgAction::SetTrue).requires("output").help("Start a new numbered output file after each silence gap instead of stopping"))
+        .arg(Arg::new("realtime").long("realtime").required(false).action(ArgAction::SetTrue).help("Raise the capture thread to real-time (SCHED_RR) scheduling"))
+        .arg(Arg::new("rate").long("rate").required(false).value_parser(value_parser!(u32)).help("Capture sample rate in Hz"))
+        .arg(Arg::new("channels").long("channels").required(false).value_parser(value_parser!(u16)).help("Number of capture channels"))
+        .arg(Arg::new("sample-format").long("sample-format").required(false).value_parser(value_parser!(SampleFormat)).help("Capture sample format: i16 or f32"))
+        .arg(Arg::new("encoding").long("encoding").required(false).value_parser(value_parser!(Encoding)).help("Output container/codec (defaults to the --output extension, then wav)"))
+        .arg(Arg::new("dump-pcm").long("dump-pcm").required(false).value_parser(value_parser!(PathBuf)).help("Decode a file and write raw interleaved PCM samples to --output (or stdout)"))
         .get_matches();
 
-    if matches.get_one::<PathBuf>("play").is_some() {
-        let file = matches.get_one::<PathBuf>("play").unwrap();
-        audio_recorder::player::play(file);
+    if let Some(input) = matches.get_one::<PathBuf>("dump-pcm") {
+        audio_recorder::player::dump_pcm(
+            input,
+            matches.get_one::<PathBuf>("output"),
+            matches.get_one::<u32>("rate").copied(),
+            matches.get_one::<u16>("channels").copied(),
+        );
+        return;
+    }
+
+    if let Some(paths) = matches.get_many::<PathBuf>("play") {
+        let paths: Vec<PathBuf> = paths.cloned().collect();
+        let volume = matches.get_one::<f32>("volume").copied();
+        let speed = matches.get_one::<f32>("speed").copied();
+        let handle = audio_recorder::player::play(&paths, matches.get_flag("shuffle"), matches.get_flag("repeat"), volume, speed);
+
+        if let Some(handle) = handle {
+            if matches.get_flag("interactive") {
+                audio_recorder::player::interactive_control(&handle);
+            } else {
+                handle.sleep_until_end();
+            }
+        }
         return;
     }
 
@@ -46,5 +79,17 @@ fn main() {
         return;
     }
 
-    recorder::record(device, output, lib, stop_silence);
+    recorder::record_with_options(recorder::RecordOptions {
+        device,
+        output,
+        lib,
+        stop_silence,
+        silence_threshold_dbfs: *matches.get_one::<f32>("silence-threshold").unwrap(),
+        split_on_silence: matches.get_flag("split-on-silence"),
+        realtime: matches.get_flag("realtime"),
+        sample_rate: matches.get_one::<u32>("rate").copied(),
+        channels: matches.get_one::<u16>("channels").copied(),
+        sample_format: matches.get_one::<SampleFormat>("sample-format").copied(),
+        encoding: matches.get_one::<Encoding>("encoding").copied(),
+    });
 }
\ No newline at end of file
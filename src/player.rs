@@ -1,14 +1,222 @@
 use std::fs::File;
-use std::io::BufReader;
-use std::path::PathBuf;
-use rodio::{Decoder, OutputStream, Sink};
-
-pub fn play(file: &PathBuf) {
-    let (_stream, stream_handle) = OutputStream::try_default().unwrap();
-    let sink = Sink::try_new(&stream_handle).unwrap();
-    let file = File::open(file).unwrap();
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::thread::JoinHandle;
+
+use rand::seq::SliceRandom;
+use rand::thread_rng;
+use rodio::source::UniformSourceIterator;
+use rodio::{Decoder, OutputStream, Sink, Source};
+
+/// Extensions we try to decode when walking a directory; anything else is skipped rather
+/// than failing the whole queue.
+const SUPPORTED_EXTENSIONS: &[&str] = &["mp3", "wav", "flac", "ogg", "m4a", "aac"];
+
+/// Volume is stepped by this much per `+`/`-` keypress in `interactive_control`.
+const VOLUME_STEP: f32 = 0.1;
+
+fn is_decodable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| SUPPORTED_EXTENSIONS.iter().any(|candidate| candidate.eq_ignore_ascii_case(ext)))
+        .unwrap_or(false)
+}
+
+/// Expand a mix of files and directories into a flat list of playable tracks. Paths keep
+/// the order they were given in; each directory's entries are sorted by path.
+fn collect_tracks(paths: &[PathBuf]) -> Vec<PathBuf> {
+    let mut tracks = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = std::fs::read_dir(path)
+                .unwrap_or_else(|e| panic!("failed to read directory {}: {}", path.display(), e))
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|p| p.is_file() && is_decodable(p))
+                .collect();
+            entries.sort();
+            tracks.extend(entries);
+        } else if is_decodable(path) {
+            tracks.push(path.clone());
+        }
+    }
+    tracks
+}
+
+/// Append each track to the queue, skipping (with a warning) any file that can't actually
+/// be opened or decoded rather than unwrapping: `is_decodable` only checks the extension,
+/// so a whitelisted-but-truncated/corrupt file shouldn't be allowed to kill the whole queue.
+fn append_queue(sink: &Sink, tracks: &[PathBuf]) {
+    for track in tracks {
+        let file = match File::open(track) {
+            Ok(file) => file,
+            Err(e) => {
+                eprintln!("skipping {}: {}", track.display(), e);
+                continue;
+            }
+        };
+        let reader = BufReader::new(file);
+        let source = match Decoder::new(reader) {
+            Ok(source) => source,
+            Err(e) => {
+                eprintln!("skipping {}: {}", track.display(), e);
+                continue;
+            }
+        };
+        sink.append(source);
+    }
+}
+
+/// A handle to an in-progress playback session. Dropping it stops playback, since the
+/// underlying `OutputStream` is closed along with it.
+pub struct PlayHandle {
+    sink: Arc<Sink>,
+    _stream: OutputStream,
+    stopped: Arc<AtomicBool>,
+    repeat_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl PlayHandle {
+    pub fn pause(&self) {
+        self.sink.pause();
+    }
+
+    pub fn resume(&self) {
+        self.sink.play();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.sink.volume()
+    }
+
+    pub fn set_volume(&self, volume: f32) {
+        self.sink.set_volume(volume.clamp(0.0, 2.0));
+    }
+
+    pub fn set_speed(&self, speed: f32) {
+        self.sink.set_speed(speed);
+    }
+
+    /// Stop playback for good. Sets an explicit flag so the repeat thread (if any) knows
+    /// the sink draining is because we were stopped, not just between tracks.
+    pub fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        self.sink.stop();
+    }
+
+    /// Block until playback is done: a single pass through the queue, or, when `repeat`
+    /// was requested, until `stop` is called. Joining the repeat thread here (rather than
+    /// sleeping on the sink ourselves) avoids racing it to notice the queue drained.
+    pub fn sleep_until_end(&self) {
+        if let Some(handle) = self.repeat_thread.lock().unwrap().take() {
+            handle.join().ok();
+        } else {
+            self.sink.sleep_until_end();
+        }
+    }
+}
+
+/// Play one or more files (or directories of files) back to back on a single `Sink`, so
+/// there is no gap between tracks, and return a `PlayHandle` for the caller to drive.
+/// `shuffle` randomizes the queue order once up front; `repeat` replays the (possibly
+/// shuffled) queue forever in a background thread, until `PlayHandle::stop` is called.
+/// Returns `None` if no playable files were found.
+pub fn play(paths: &[PathBuf], shuffle: bool, repeat: bool, volume: Option<f32>, speed: Option<f32>) -> Option<PlayHandle> {
+    let mut tracks = collect_tracks(paths);
+    if tracks.is_empty() {
+        eprintln!("No playable audio files found in {:?}", paths);
+        return None;
+    }
+
+    if shuffle {
+        tracks.shuffle(&mut thread_rng());
+    }
+
+    let (stream, stream_handle) = OutputStream::try_default().unwrap();
+    let sink = Arc::new(Sink::try_new(&stream_handle).unwrap());
+
+    if let Some(volume) = volume {
+        sink.set_volume(volume.clamp(0.0, 2.0));
+    }
+    if let Some(speed) = speed {
+        sink.set_speed(speed);
+    }
+
+    append_queue(&sink, &tracks);
+
+    let stopped = Arc::new(AtomicBool::new(false));
+    let repeat_thread = if repeat {
+        let sink = Arc::clone(&sink);
+        let stopped = Arc::clone(&stopped);
+        Some(thread::spawn(move || loop {
+            sink.sleep_until_end();
+            if stopped.load(Ordering::SeqCst) {
+                break;
+            }
+            append_queue(&sink, &tracks);
+        }))
+    } else {
+        None
+    };
+
+    Some(PlayHandle { sink, _stream: stream, stopped, repeat_thread: Mutex::new(repeat_thread) })
+}
+
+/// Decode `input` (via the same `Decoder` path used by `play`) and write its raw
+/// interleaved `f32` PCM samples to `output`, or to stdout when `output` is `None`.
+/// `target_rate`/`target_channels` resample and remix the output when given; otherwise
+/// the source file's own format is kept.
+pub fn dump_pcm(input: &PathBuf, output: Option<&PathBuf>, target_rate: Option<u32>, target_channels: Option<u16>) {
+    let file = File::open(input).unwrap();
     let reader = BufReader::new(file);
     let source = Decoder::new(reader).unwrap();
-    sink.append(source);
-    sink.sleep_until_end();
-}
\ No newline at end of file
+
+    let channels = target_channels.unwrap_or_else(|| source.channels());
+    let sample_rate = target_rate.unwrap_or_else(|| source.sample_rate());
+    let samples: UniformSourceIterator<_, f32> = UniformSourceIterator::new(source, channels, sample_rate);
+
+    let mut writer: Box<dyn Write> = match output {
+        Some(path) => Box::new(BufWriter::new(File::create(path).unwrap())),
+        None => Box::new(io::stdout().lock()),
+    };
+
+    for sample in samples {
+        writer.write_all(&sample.to_le_bytes()).unwrap();
+    }
+    writer.flush().unwrap();
+}
+
+/// Drive playback from stdin: an empty line (space then enter) toggles pause/resume,
+/// `+`/`-` nudge the volume, and `q` stops playback and returns. Lines are used instead
+/// of raw keypresses since this crate has no terminal-raw-mode dependency.
+pub fn interactive_control(handle: &PlayHandle) {
+    println!("[space] pause/resume  [+/-] volume  [q] stop");
+
+    let stdin = io::stdin();
+    for line in stdin.lock().lines() {
+        let Ok(line) = line else { break };
+
+        match line.trim() {
+            "q" => {
+                handle.stop();
+                break;
+            }
+            "+" => handle.set_volume(handle.volume() + VOLUME_STEP),
+            "-" => handle.set_volume(handle.volume() - VOLUME_STEP),
+            _ => {
+                if handle.is_paused() {
+                    handle.resume();
+                } else {
+                    handle.pause();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,518 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use hound::{WavSpec, WavWriter};
+
+const USER_ERROR: i32 = 2;
+
+/// How long a window of samples is evaluated over when computing dBFS for silence
+/// detection (`--stop-silence`/`--split-on-silence`).
+const SILENCE_WINDOW_MS: u64 = 20;
+
+/// How much audio is kept behind the current position so a new `--split-on-silence`
+/// segment doesn't clip the onset of the sound that ended the silence.
+const PREROLL_MS: u64 = 300;
+
+/// The capture sample format requested via `--sample-format`. A plain recording is
+/// written in this format; `--split-on-silence` segments are always 32-bit float.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleFormat {
+    I16,
+    F32,
+}
+
+impl FromStr for SampleFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "i16" => Ok(SampleFormat::I16),
+            "f32" => Ok(SampleFormat::F32),
+            other => Err(format!("unsupported sample format '{}', expected i16 or f32", other)),
+        }
+    }
+}
+
+impl From<SampleFormat> for cpal::SampleFormat {
+    fn from(format: SampleFormat) -> Self {
+        match format {
+            SampleFormat::I16 => cpal::SampleFormat::I16,
+            SampleFormat::F32 => cpal::SampleFormat::F32,
+        }
+    }
+}
+
+/// The on-disk container/codec requested via `--encoding` (or inferred from `--output`'s
+/// extension). WAV is the only one implemented so far.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Encoding {
+    Wav,
+}
+
+impl FromStr for Encoding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "wav" => Ok(Encoding::Wav),
+            other => Err(format!("unsupported encoding '{}', only wav is supported", other)),
+        }
+    }
+}
+
+/// Parameters controlling a capture session. Mirrors the CLI arguments in `main.rs`.
+pub struct RecordOptions<'a> {
+    pub device: Option<&'a i32>,
+    pub output: Option<&'a PathBuf>,
+    pub lib: Option<&'a PathBuf>,
+    pub stop_silence: Option<&'a u64>,
+    pub silence_threshold_dbfs: f32,
+    pub split_on_silence: bool,
+    pub realtime: bool,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u16>,
+    pub sample_format: Option<SampleFormat>,
+    pub encoding: Option<Encoding>,
+}
+
+fn input_devices(host: &cpal::Host) -> Vec<cpal::Device> {
+    host.input_devices().map(|devices| devices.collect()).unwrap_or_default()
+}
+
+pub fn list_devices(_lib: Option<&PathBuf>) {
+    let host = cpal::default_host();
+    for (index, device) in input_devices(&host).into_iter().enumerate() {
+        println!("{}: {}", index, device.name().unwrap_or_else(|_| "<unknown>".to_string()));
+    }
+}
+
+fn select_device(host: &cpal::Host, device: Option<&i32>) -> cpal::Device {
+    match device {
+        Some(index) => input_devices(host)
+            .into_iter()
+            .nth(*index as usize)
+            .expect("no input device with that index"),
+        None => host.default_input_device().expect("no default input device"),
+    }
+}
+
+/// Pick a supported input config matching the requested rate/channels/sample-format, or
+/// print a clear error and exit `USER_ERROR` (mirroring how `--stream`/`--output` conflicts
+/// are reported in `main.rs`) when the device can't satisfy the request.
+fn resolve_config(
+    device: &cpal::Device,
+    rate: Option<u32>,
+    channels: Option<u16>,
+    sample_format: Option<SampleFormat>,
+) -> cpal::SupportedStreamConfig {
+    if rate.is_none() && channels.is_none() && sample_format.is_none() {
+        return device.default_input_config().expect("no supported input config");
+    }
+
+    let supported: Vec<_> = device
+        .supported_input_configs()
+        .expect("failed to query supported input configs")
+        .collect();
+
+    let candidate = supported.into_iter().find(|range| {
+        let rate_ok = rate.is_none_or(|r| r >= range.min_sample_rate().0 && r <= range.max_sample_rate().0);
+        let channels_ok = channels.is_none_or(|c| c == range.channels());
+        let format_ok = sample_format.is_none_or(|f| cpal::SampleFormat::from(f) == range.sample_format());
+        rate_ok && channels_ok && format_ok
+    });
+
+    match candidate {
+        Some(range) => {
+            let default_rate = range.max_sample_rate().0.min(44_100).max(range.min_sample_rate().0);
+            range.with_sample_rate(cpal::SampleRate(rate.unwrap_or(default_rate)))
+        }
+        None => {
+            eprintln!(
+                "Unsupported capture format for this device: rate={:?} channels={:?} sample_format={:?}",
+                rate, channels, sample_format
+            );
+            std::process::exit(USER_ERROR);
+        }
+    }
+}
+
+/// Resolve the output encoding: an explicit `--encoding` wins, otherwise it's inferred
+/// from `--output`'s extension, defaulting to WAV when neither is given.
+fn resolve_encoding(encoding: Option<Encoding>, output: Option<&PathBuf>) -> Encoding {
+    if let Some(encoding) = encoding {
+        return encoding;
+    }
+
+    if let Some(extension) = output.and_then(|path| path.extension()).and_then(|ext| ext.to_str()) {
+        return extension.parse().unwrap_or_else(|err: String| {
+            eprintln!("{}", err);
+            std::process::exit(USER_ERROR);
+        });
+    }
+
+    Encoding::Wav
+}
+
+/// Raise the calling thread to `SCHED_RR` real-time scheduling. Recording is latency
+/// sensitive, and missing a deadline under the default scheduler causes audible glitches.
+/// Lacking the privilege to do this (no `CAP_SYS_NICE` / not root) is common and not
+/// fatal: we warn and keep running at the default priority.
+fn raise_realtime_priority() {
+    unsafe {
+        let param = libc::sched_param { sched_priority: 50 };
+        if libc::sched_setscheduler(0, libc::SCHED_RR, &param) != 0 {
+            eprintln!(
+                "warning: could not enable real-time scheduling, continuing at the default priority: {}",
+                std::io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+static REALTIME_ONCE: Once = Once::new();
+
+/// Raise `--realtime`'s priority on whichever thread calls this, the first time it's
+/// called. Must be called from inside the `cpal` capture callback: that's the thread that
+/// actually does the latency-sensitive work, not the control thread that merely polls
+/// `stop` and waits for the stream to finish.
+fn ensure_realtime_priority(realtime: bool) {
+    if realtime {
+        REALTIME_ONCE.call_once(raise_realtime_priority);
+    }
+}
+
+/// Install a SIGINT/SIGTERM handler that flips an `AtomicBool` the capture loop polls,
+/// so Ctrl-C (or a `kill`) stops recording cleanly and the output file is finalized
+/// instead of left with a truncated RIFF header.
+fn install_stop_signal() -> Arc<AtomicBool> {
+    let stop = Arc::new(AtomicBool::new(false));
+    signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&stop))
+        .expect("failed to register SIGINT handler");
+    signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&stop))
+        .expect("failed to register SIGTERM handler");
+    stop
+}
+
+/// Tracks the dBFS level over consecutive, non-overlapping windows of samples, and how
+/// long the signal has continuously stayed below `threshold_dbfs`.
+struct SilenceMonitor {
+    threshold_dbfs: f32,
+    window: Vec<f32>,
+    window_len: usize,
+    silent_ms: u64,
+}
+
+impl SilenceMonitor {
+    fn new(channels: u16, sample_rate: u32, threshold_dbfs: f32) -> Self {
+        let window_len = ((sample_rate as u64 * channels as u64 * SILENCE_WINDOW_MS) / 1000).max(1) as usize;
+        SilenceMonitor { threshold_dbfs, window: Vec::with_capacity(window_len), window_len, silent_ms: 0 }
+    }
+
+    /// Feed one normalized [-1.0, 1.0] sample. Returns whether the window that just
+    /// completed was silent, or `None` while the window is still filling up.
+    fn push(&mut self, sample: f32) -> Option<bool> {
+        self.window.push(sample);
+        if self.window.len() < self.window_len {
+            return None;
+        }
+
+        let mean_square = self.window.iter().map(|s| s * s).sum::<f32>() / self.window.len() as f32;
+        let rms = mean_square.sqrt();
+        let dbfs = if rms < 1e-9 { f32::NEG_INFINITY } else { 20.0 * rms.log10() };
+        let is_silent = dbfs < self.threshold_dbfs;
+
+        self.silent_ms = if is_silent { self.silent_ms + SILENCE_WINDOW_MS } else { 0 };
+        self.window.clear();
+        Some(is_silent)
+    }
+
+    fn silent_for_ms(&self) -> u64 {
+        self.silent_ms
+    }
+}
+
+pub fn record_with_options(options: RecordOptions) {
+    // Only WAV is implemented, but resolving it now surfaces an unsupported
+    // `--encoding`/extension before we've opened an input stream.
+    let _encoding = resolve_encoding(options.encoding, options.output);
+
+    if options.split_on_silence && options.output.is_none() {
+        eprintln!("--split-on-silence requires --output to number the segment files");
+        std::process::exit(USER_ERROR);
+    }
+
+    let stop = install_stop_signal();
+
+    let host = cpal::default_host();
+    let device = select_device(&host, options.device);
+    let config = resolve_config(&device, options.sample_rate, options.channels, options.sample_format);
+
+    if options.split_on_silence {
+        run_split_on_silence(&device, &config, &options, &stop);
+        return;
+    }
+
+    let spec = WavSpec {
+        channels: config.channels(),
+        sample_rate: config.sample_rate().0,
+        bits_per_sample: match config.sample_format() {
+            cpal::SampleFormat::I16 => 16,
+            _ => 32,
+        },
+        sample_format: match config.sample_format() {
+            cpal::SampleFormat::I16 => hound::SampleFormat::Int,
+            _ => hound::SampleFormat::Float,
+        },
+    };
+
+    let writer = options.output.map(|path| {
+        Arc::new(Mutex::new(
+            WavWriter::create(path, spec).expect("failed to create output file"),
+        ))
+    });
+
+    let stop_silence = options.stop_silence.copied();
+    let monitor = Arc::new(Mutex::new(SilenceMonitor::new(spec.channels, spec.sample_rate, options.silence_threshold_dbfs)));
+    let realtime = options.realtime;
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I16 => {
+            let stream_writer = writer.clone();
+            let stream_stop = Arc::clone(&stop);
+            let stream_monitor = Arc::clone(&monitor);
+            device.build_input_stream(
+                &config.into(),
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    ensure_realtime_priority(realtime);
+                    write_samples(data, &stream_writer, stop_silence, &stream_monitor, &stream_stop);
+                },
+                |err| eprintln!("stream error: {}", err),
+                None,
+            )
+        }
+        _ => {
+            let stream_writer = writer.clone();
+            let stream_stop = Arc::clone(&stop);
+            let stream_monitor = Arc::clone(&monitor);
+            device.build_input_stream(
+                &config.into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    ensure_realtime_priority(realtime);
+                    write_samples(data, &stream_writer, stop_silence, &stream_monitor, &stream_stop);
+                },
+                |err| eprintln!("stream error: {}", err),
+                None,
+            )
+        }
+    }
+    .expect("failed to build input stream");
+
+    stream.play().expect("failed to start input stream");
+
+    while !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    drop(stream);
+
+    if let Some(writer) = writer {
+        let writer = Arc::try_unwrap(writer)
+            .unwrap_or_else(|_| panic!("writer still shared after stream was dropped"))
+            .into_inner()
+            .unwrap();
+        writer.finalize().expect("failed to finalize WAV file");
+    }
+}
+
+/// Shared capture callback body: write each sample to the output (or stdout when
+/// streaming), and stop recording once `stop_silence` ms of continuous sub-threshold
+/// signal has been seen.
+fn write_samples<S>(
+    data: &[S],
+    writer: &Option<Arc<Mutex<WavWriter<BufWriter<File>>>>>,
+    stop_silence: Option<u64>,
+    monitor: &Arc<Mutex<SilenceMonitor>>,
+    stop: &AtomicBool,
+) where
+    S: hound::Sample + cpal::Sample<Float = f32> + Copy + std::fmt::Display,
+{
+    if let Some(writer) = writer {
+        let mut writer = writer.lock().unwrap();
+        for &sample in data {
+            writer.write_sample(sample).ok();
+        }
+    } else {
+        for &sample in data {
+            println!("{}", sample);
+        }
+    }
+
+    if let Some(limit_ms) = stop_silence {
+        let mut monitor = monitor.lock().unwrap();
+        for &sample in data {
+            // `to_float_sample` normalizes to [-1.0, 1.0] regardless of the capture
+            // format; a plain `Into<f32>` cast would leave an `i16` stream at its raw
+            // integer magnitude and the dBFS math would never see silence.
+            if let Some(true) = monitor.push(sample.to_float_sample()) {
+                if monitor.silent_for_ms() >= limit_ms {
+                    stop.store(true, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+}
+
+/// Segmented output for `--split-on-silence`: writes numbered WAV files as 32-bit float,
+/// since segment boundaries are decided from the float RMS calculation in `SilenceMonitor`.
+struct SegmentWriter {
+    base: PathBuf,
+    spec: WavSpec,
+    index: u32,
+    current: Option<WavWriter<BufWriter<File>>>,
+}
+
+impl SegmentWriter {
+    fn new(base: PathBuf, channels: u16, sample_rate: u32) -> Self {
+        let spec = WavSpec { channels, sample_rate, bits_per_sample: 32, sample_format: hound::SampleFormat::Float };
+        SegmentWriter { base, spec, index: 0, current: None }
+    }
+
+    fn segment_path(&self) -> PathBuf {
+        let stem = self.base.file_stem().and_then(|s| s.to_str()).unwrap_or("segment");
+        let extension = self.base.extension().and_then(|s| s.to_str()).unwrap_or("wav");
+        self.base.with_file_name(format!("{}_{:03}.{}", stem, self.index, extension))
+    }
+
+    fn open_next(&mut self) {
+        self.index += 1;
+        let path = self.segment_path();
+        self.current = Some(WavWriter::create(&path, self.spec).expect("failed to create output segment"));
+    }
+
+    fn write(&mut self, sample: f32) {
+        if let Some(writer) = &mut self.current {
+            writer.write_sample(sample).ok();
+        }
+    }
+
+    fn close(&mut self) {
+        if let Some(writer) = self.current.take() {
+            writer.finalize().expect("failed to finalize WAV segment");
+        }
+    }
+}
+
+/// Shared state for the `--split-on-silence` capture callback: whether we're currently
+/// writing a segment, and a short pre-roll ring buffer so the onset of a segment (the
+/// audio right after a silence gap) isn't clipped.
+struct SplitState {
+    writer: SegmentWriter,
+    preroll: VecDeque<f32>,
+    preroll_capacity: usize,
+    recording: bool,
+}
+
+fn write_segmented_samples<S>(data: &[S], state: &Arc<Mutex<SplitState>>, monitor: &Arc<Mutex<SilenceMonitor>>, stop_after_ms: u64)
+where
+    S: Copy + cpal::Sample<Float = f32>,
+{
+    let mut state = state.lock().unwrap();
+    let mut monitor = monitor.lock().unwrap();
+
+    for &raw in data {
+        // Normalized once here and reused for the segment WAV, the preroll buffer, and
+        // the silence monitor, so an i16 stream can't end up writing ~32000x-out-of-range
+        // samples into the (always 32-bit float) segment files.
+        let sample: f32 = raw.to_float_sample();
+
+        if state.recording {
+            state.writer.write(sample);
+        }
+
+        state.preroll.push_back(sample);
+        if state.preroll.len() > state.preroll_capacity {
+            state.preroll.pop_front();
+        }
+
+        if let Some(is_silent) = monitor.push(sample) {
+            if is_silent && state.recording && monitor.silent_for_ms() >= stop_after_ms {
+                state.writer.close();
+                state.recording = false;
+            } else if !is_silent && !state.recording {
+                state.writer.open_next();
+                let preroll: Vec<f32> = state.preroll.drain(..).collect();
+                for preroll_sample in preroll {
+                    state.writer.write(preroll_sample);
+                }
+                state.recording = true;
+            }
+        }
+    }
+}
+
+/// Record into numbered segment files, closing the current one after `--stop-silence` ms
+/// below `--silence-threshold` and opening the next once the signal returns.
+fn run_split_on_silence(device: &cpal::Device, config: &cpal::SupportedStreamConfig, options: &RecordOptions, stop: &Arc<AtomicBool>) {
+    let output = options.output.expect("--split-on-silence requires --output").clone();
+    let stop_after_ms = options.stop_silence.copied().unwrap_or(500);
+    let channels = config.channels();
+    let sample_rate = config.sample_rate().0;
+
+    let preroll_capacity = ((sample_rate as u64 * channels as u64 * PREROLL_MS) / 1000).max(1) as usize;
+    let state = Arc::new(Mutex::new(SplitState {
+        writer: SegmentWriter::new(output, channels, sample_rate),
+        preroll: VecDeque::with_capacity(preroll_capacity),
+        preroll_capacity,
+        recording: false,
+    }));
+    let monitor = Arc::new(Mutex::new(SilenceMonitor::new(channels, sample_rate, options.silence_threshold_dbfs)));
+    let realtime = options.realtime;
+
+    let stream = match config.sample_format() {
+        cpal::SampleFormat::I16 => {
+            let stream_state = Arc::clone(&state);
+            let stream_monitor = Arc::clone(&monitor);
+            device.build_input_stream(
+                &config.clone().into(),
+                move |data: &[i16], _: &cpal::InputCallbackInfo| {
+                    ensure_realtime_priority(realtime);
+                    write_segmented_samples(data, &stream_state, &stream_monitor, stop_after_ms);
+                },
+                |err| eprintln!("stream error: {}", err),
+                None,
+            )
+        }
+        _ => {
+            let stream_state = Arc::clone(&state);
+            let stream_monitor = Arc::clone(&monitor);
+            device.build_input_stream(
+                &config.clone().into(),
+                move |data: &[f32], _: &cpal::InputCallbackInfo| {
+                    ensure_realtime_priority(realtime);
+                    write_segmented_samples(data, &stream_state, &stream_monitor, stop_after_ms);
+                },
+                |err| eprintln!("stream error: {}", err),
+                None,
+            )
+        }
+    }
+    .expect("failed to build input stream");
+
+    stream.play().expect("failed to start input stream");
+
+    while !stop.load(Ordering::SeqCst) {
+        thread::sleep(Duration::from_millis(50));
+    }
+
+    drop(stream);
+
+    state.lock().unwrap().writer.close();
+}